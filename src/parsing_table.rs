@@ -12,6 +12,108 @@ enum ActionKind {
     Error,
 }
 
+/// `(state, token)` に対応する action が存在しなかったときに記録する診断情報.
+/// `expected` は `action_table` をその状態に絞って走査することで求めた、
+/// その場で妥当だったはずの終端記号の集合.
+#[derive(Debug, Clone)]
+pub struct SyntaxDiagnostic<T> {
+    pub token_index: usize,
+    pub found: T,
+    pub expected: BTreeSet<T>,
+}
+
+/// 還元の列が作る具象構文木. シフトは葉として終端記号を運び、還元は
+/// その規則が束ねる部分木を子として持つ内部ノードになる.
+#[derive(Debug, Clone)]
+pub enum ParseTree<NT, T> {
+    Leaf(T),
+    Node {
+        rule: usize,
+        left: NT,
+        children: Vec<ParseTree<NT, T>>,
+    },
+}
+
+/// 規則番号ごとに登録された意味アクションで構文木を葉から畳み込み、
+/// 任意の出力型 `V` を合成する。Kind 風のコード生成器が項の木を辿って
+/// ターゲット値を作るのと同じやり方.
+pub fn fold_parse_tree<NT, T, V>(
+    tree: &ParseTree<NT, T>,
+    leaf: &impl Fn(&T) -> V,
+    actions: &BTreeMap<usize, Box<dyn Fn(Vec<V>) -> V>>,
+) -> V {
+    match tree {
+        ParseTree::Leaf(t) => leaf(t),
+        ParseTree::Node { rule, children, .. } => {
+            let values = children
+                .iter()
+                .map(|child| fold_parse_tree(child, leaf, actions))
+                .collect();
+            match actions.get(rule) {
+                Some(action) => action(values),
+                None => panic!("no semantic action registered for rule {}", rule),
+            }
+        }
+    }
+}
+
+/// DOT のラベルは二重引用符で囲んだ文字列なので、`{:?}` の出力に含まれ得る
+/// `"` / `\` / 改行をエスケープしてから埋め込む。さもないと識別子や文字列
+/// リテラルを保持するトークン型で `dot` が構文エラーになる.
+fn escape_dot_label(value: &str) -> String {
+    value
+        .chars()
+        .flat_map(|c| match c {
+            '"' => vec!['\\', '"'],
+            '\\' => vec!['\\', '\\'],
+            '\n' => vec!['\\', 'n'],
+            other => vec![other],
+        })
+        .collect()
+}
+
+/// `parse_to_tree` が組み立てた構文木を GraphViz DOT 形式の木として出力する.
+/// `dot -Tpng` にそのまま渡せる.
+pub fn export_parse_as_dot<NT, T>(tree: &ParseTree<NT, T>) -> String
+where
+    NT: Debug,
+    T: Debug,
+{
+    use std::fmt::Write;
+    let mut dot = String::new();
+    writeln!(&mut dot, "digraph parse_tree {{").unwrap();
+    writeln!(&mut dot, "  node [shape=box, fontname=\"monospace\"];").unwrap();
+    let mut next_id = 0;
+    write_parse_tree_node(tree, &mut dot, &mut next_id);
+    writeln!(&mut dot, "}}").unwrap();
+    dot
+}
+
+fn write_parse_tree_node<NT, T>(tree: &ParseTree<NT, T>, dot: &mut String, next_id: &mut usize) -> usize
+where
+    NT: Debug,
+    T: Debug,
+{
+    use std::fmt::Write;
+    let id = *next_id;
+    *next_id += 1;
+    match tree {
+        ParseTree::Leaf(t) => {
+            let label = escape_dot_label(&format!("{:?}", t));
+            writeln!(dot, "  n{} [label=\"{}\", shape=ellipse];", id, label).unwrap();
+        }
+        ParseTree::Node { rule, left, children } => {
+            let label = escape_dot_label(&format!("{:?}", left));
+            writeln!(dot, "  n{} [label=\"{} (r{})\"];", id, label, rule).unwrap();
+            for child in children {
+                let child_id = write_parse_tree_node(child, dot, next_id);
+                writeln!(dot, "  n{} -> n{};", id, child_id).unwrap();
+            }
+        }
+    }
+    id
+}
+
 pub struct LR0Parser<NT, T>
 where
     NT: Debug + Clone + Eq + Ord,
@@ -23,6 +125,8 @@ where
     action_table: BTreeMap<(usize, T), ActionKind>,
     goto_table: BTreeMap<(usize, NT), usize>,
     stack: Vec<usize>,
+    // 状態番号は automaton ごとに任意に振られるため、start 状態を覚えておく.
+    start_state_number: usize,
     //rules
     rule_table: Vec<LR0Item<NT, T>>,
 }
@@ -40,6 +144,80 @@ pub fn canonical_automaton_to_lr0_parser<NT, T>(
     eof_symbol: T,
     terms: &[T],
 ) -> LR0Parser<NT, T>
+where
+    NT: Ord + Eq + Clone + Debug,
+    T: Ord + Eq + Clone + Debug,
+{
+    build_parser(
+        automaton,
+        extended_start_symbol,
+        start_symbol,
+        eof_symbol,
+        |_left| terms.to_vec(),
+    )
+}
+
+/*
+    正準オートマトンから SLR(1) 構文解析器を作成する.
+    FIRST/FOLLOW 集合を計算し、完了項 A→γ・ の還元を FOLLOW(A) に含まれる
+    終端記号上にのみ挿入することで、LR(0) 版が抱えていた見かけ上の
+    shift/reduce・reduce/reduce 衝突の大半を取り除く.
+*/
+pub fn canonical_automaton_to_slr1_parser<NT, T>(
+    automaton: (
+        &[Vec<LR0Item<NT, T>>],
+        &BTreeMap<(Vec<LR0Item<NT, T>>, Symbol<NT, T>), Vec<LR0Item<NT, T>>>,
+    ),
+    extended_start_symbol: NT,
+    start_symbol: NT,
+    eof_symbol: T,
+    terms: &[T],
+) -> LR0Parser<NT, T>
+where
+    NT: Ord + Eq + Clone + Debug,
+    T: Ord + Eq + Clone + Debug,
+{
+    let productions = collect_productions(automaton.0);
+    let (first_sets, nullable) = compute_first_sets(&productions);
+    let follow_sets = compute_follow_sets(
+        &productions,
+        &first_sets,
+        &nullable,
+        &start_symbol,
+        &eof_symbol,
+    );
+    let terms_set: BTreeSet<T> = terms.iter().cloned().collect();
+
+    build_parser(
+        automaton,
+        extended_start_symbol,
+        start_symbol,
+        eof_symbol,
+        move |left| {
+            follow_sets
+                .get(left)
+                .map(|follow| follow.iter().filter(|t| terms_set.contains(t)).cloned().collect())
+                .unwrap_or_default()
+        },
+    )
+}
+
+/*
+    正準オートマトンから構文解析器を組み立てる共通部分.
+    `reduce_lookahead` は完了項の左辺非終端記号を受け取り、その還元を
+    挿入すべき終端記号の集合を返す。LR(0) は常に全終端記号、SLR(1) は
+    FOLLOW 集合との積を渡すことで両方の構成法を共有する.
+*/
+fn build_parser<NT, T>(
+    automaton: (
+        &[Vec<LR0Item<NT, T>>],
+        &BTreeMap<(Vec<LR0Item<NT, T>>, Symbol<NT, T>), Vec<LR0Item<NT, T>>>,
+    ),
+    extended_start_symbol: NT,
+    start_symbol: NT,
+    eof_symbol: T,
+    mut reduce_lookahead: impl FnMut(&NT) -> Vec<T>,
+) -> LR0Parser<NT, T>
 where
     NT: Ord + Eq + Clone + Debug,
     T: Ord + Eq + Clone + Debug,
@@ -73,38 +251,44 @@ where
     };
     let mut action_table = BTreeMap::new();
     let mut goto_table = BTreeMap::new();
-    //還元を行う状態の集合.
-    let reduce_states: BTreeSet<_> = automaton
-        .0
-        .iter()
-        .filter(|state| {
-            let reduce_state: BTreeSet<_> = state
-                .iter()
-                .filter(|lr0item| {
-                    lr0item.dot_pos == lr0item.right.len() && ((*lr0item).clone() != accept_rule)
-                })
-                .collect();
-            if reduce_state.len() > 1 {
-                eprintln!("Reduce/Reduce conflict detected.");
-                true
-            } else if reduce_state.len() == 1 && state.len() > 1 {
+    let mut rule_table = vec![];
+    // 完了項ごとに独立して還元を登録する。各項の `reduce_lookahead` が返す
+    // 終端記号集合が他の項（完了項どうし、あるいは同じ状態のシフト項）
+    // と重なったときだけ、本当の衝突として警告する.
+    for state in automaton.0 {
+        let completed_items: Vec<_> = state
+            .iter()
+            .filter(|item| item.dot_pos == item.right.len() && **item != accept_rule)
+            .collect();
+        if completed_items.is_empty() {
+            continue;
+        }
+        let Some(state_number) = state_number_table.get(state) else {
+            continue;
+        };
+        let shift_terms: BTreeSet<T> = state
+            .iter()
+            .filter(|item| item.dot_pos < item.right.len())
+            .filter_map(|item| match &item.right[item.dot_pos] {
+                Symbol::Term(t) => Some(t.clone()),
+                Symbol::NonTerm(_) => None,
+            })
+            .collect();
+        let mut claimed_terms: BTreeSet<T> = BTreeSet::new();
+        for item in completed_items {
+            let rule_number = rule_table.len();
+            let lookahead: BTreeSet<T> = reduce_lookahead(&item.left).into_iter().collect();
+            if lookahead.intersection(&shift_terms).next().is_some() {
                 println!(" Shift/Reduce conflict detected.");
-                true
-            } else {
-                reduce_state.len() == 1
             }
-        })
-        .collect();
-    let mut rule_table = vec![];
-    for (rule_number, reduce_state) in reduce_states.iter().enumerate() {
-        if let Some(state_number) = state_number_table.get(*reduce_state) {
-            rule_table.push(reduce_state[0].clone());
-            for term in terms {
-                action_table.insert(
-                    (*state_number, term.clone()),
-                    ActionKind::Reduce(rule_number),
-                );
+            if lookahead.intersection(&claimed_terms).next().is_some() {
+                eprintln!("Reduce/Reduce conflict detected.");
             }
+            claimed_terms.extend(lookahead.iter().cloned());
+            for term in lookahead {
+                action_table.insert((*state_number, term), ActionKind::Reduce(rule_number));
+            }
+            rule_table.push(item.clone());
         }
     }
 
@@ -136,10 +320,226 @@ where
         action_table,
         goto_table,
         stack: vec![*start_state_number],
+        start_state_number: *start_state_number,
         rule_table,
     }
 }
 
+/// 正準オートマトンを GraphViz DOT 形式で出力する。各状態 `q_n` は
+/// 含まれる LR0 項を `•` 付きでラベルに並べたノードになり、
+/// goto/shift 遷移は `Symbol` でラベル付けした辺として描かれる。
+/// Accept に至る遷移だけ色を変えて目立たせる。`dot -Tpng` に渡せば
+/// そのままオートマトンを図として描画できる.
+pub fn export_automaton_as_dot<NT, T>(
+    automaton: (
+        &[Vec<LR0Item<NT, T>>],
+        &BTreeMap<(Vec<LR0Item<NT, T>>, Symbol<NT, T>), Vec<LR0Item<NT, T>>>,
+    ),
+    extended_start_symbol: NT,
+    start_symbol: NT,
+    eof_symbol: T,
+) -> String
+where
+    NT: Ord + Eq + Clone + Debug,
+    T: Ord + Eq + Clone + Debug,
+{
+    use std::fmt::Write;
+
+    let state_number_table: BTreeMap<_, _> = automaton
+        .0
+        .iter()
+        .enumerate()
+        .map(|(id, state)| (state.clone(), id))
+        .collect();
+    let accept_rule = LR0Item {
+        left: extended_start_symbol,
+        right: vec![Symbol::NonTerm(start_symbol), Symbol::Term(eof_symbol)],
+        dot_pos: 2,
+    };
+
+    let mut dot = String::new();
+    writeln!(&mut dot, "digraph automaton {{").unwrap();
+    writeln!(&mut dot, "  rankdir=LR;").unwrap();
+    writeln!(&mut dot, "  node [shape=box, fontname=\"monospace\"];").unwrap();
+
+    for (state, id) in &state_number_table {
+        let mut label = format!("q{}\\n", id);
+        for item in state {
+            write!(&mut label, "{} -> ", escape_dot_label(&format!("{:?}", item.left))).unwrap();
+            for (pos, symbol) in item.right.iter().enumerate() {
+                if pos == item.dot_pos {
+                    label.push_str("\u{2022} ");
+                }
+                write!(&mut label, "{} ", escape_dot_label(&format!("{:?}", symbol))).unwrap();
+            }
+            if item.dot_pos == item.right.len() {
+                label.push('\u{2022}');
+            }
+            label.push_str("\\n");
+        }
+        writeln!(&mut dot, "  q{} [label=\"{}\"];", id, label).unwrap();
+    }
+
+    for ((from, symbol), to) in automaton.1 {
+        if to.is_empty() {
+            continue;
+        }
+        let from_id = state_number_table.get(from).unwrap();
+        let to_id = state_number_table.get(to).unwrap();
+        let style = if to.contains(&accept_rule) {
+            ", color=green, penwidth=2"
+        } else {
+            ""
+        };
+        let label = escape_dot_label(&format!("{:?}", symbol));
+        writeln!(
+            &mut dot,
+            "  q{} -> q{} [label=\"{}\"{}];",
+            from_id, to_id, label, style
+        )
+        .unwrap();
+    }
+
+    writeln!(&mut dot, "}}").unwrap();
+    dot
+}
+
+/// 全状態の項のうち dot_pos == 0 のものを集め、文法の生成規則の集合を復元する.
+fn collect_productions<NT, T>(states: &[Vec<LR0Item<NT, T>>]) -> BTreeSet<(NT, Vec<Symbol<NT, T>>)>
+where
+    NT: Ord + Clone,
+    T: Ord + Clone,
+{
+    states
+        .iter()
+        .flatten()
+        .filter(|item| item.dot_pos == 0)
+        .map(|item| (item.left.clone(), item.right.clone()))
+        .collect()
+}
+
+/// FIRST 集合を不動点反復で計算する。併せて空列を導出できる非終端記号の集合も返す.
+fn compute_first_sets<NT, T>(
+    productions: &BTreeSet<(NT, Vec<Symbol<NT, T>>)>,
+) -> (BTreeMap<NT, BTreeSet<T>>, BTreeSet<NT>)
+where
+    NT: Ord + Clone,
+    T: Ord + Clone,
+{
+    let mut first: BTreeMap<NT, BTreeSet<T>> = BTreeMap::new();
+    let mut nullable: BTreeSet<NT> = BTreeSet::new();
+    for (left, _) in productions {
+        first.entry(left.clone()).or_default();
+    }
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (left, right) in productions {
+            if right.is_empty() {
+                changed |= nullable.insert(left.clone());
+                continue;
+            }
+            let mut all_nullable = true;
+            for symbol in right {
+                match symbol {
+                    Symbol::Term(t) => {
+                        changed |= first.get_mut(left).unwrap().insert(t.clone());
+                        all_nullable = false;
+                        break;
+                    }
+                    Symbol::NonTerm(nt) => {
+                        let nt_first = first.get(nt).cloned().unwrap_or_default();
+                        let entry = first.get_mut(left).unwrap();
+                        for t in nt_first {
+                            changed |= entry.insert(t);
+                        }
+                        if !nullable.contains(nt) {
+                            all_nullable = false;
+                            break;
+                        }
+                    }
+                }
+            }
+            if all_nullable {
+                changed |= nullable.insert(left.clone());
+            }
+        }
+    }
+
+    (first, nullable)
+}
+
+/// FOLLOW 集合を不動点反復で計算する.
+fn compute_follow_sets<NT, T>(
+    productions: &BTreeSet<(NT, Vec<Symbol<NT, T>>)>,
+    first_sets: &BTreeMap<NT, BTreeSet<T>>,
+    nullable: &BTreeSet<NT>,
+    start_symbol: &NT,
+    eof_symbol: &T,
+) -> BTreeMap<NT, BTreeSet<T>>
+where
+    NT: Ord + Clone,
+    T: Ord + Clone,
+{
+    let first_of_symbol = |symbol: &Symbol<NT, T>| -> BTreeSet<T> {
+        match symbol {
+            Symbol::Term(t) => BTreeSet::from([t.clone()]),
+            Symbol::NonTerm(nt) => first_sets.get(nt).cloned().unwrap_or_default(),
+        }
+    };
+    let is_nullable = |symbol: &Symbol<NT, T>| -> bool {
+        match symbol {
+            Symbol::Term(_) => false,
+            Symbol::NonTerm(nt) => nullable.contains(nt),
+        }
+    };
+
+    let mut follow: BTreeMap<NT, BTreeSet<T>> = BTreeMap::new();
+    for (left, _) in productions {
+        follow.entry(left.clone()).or_default();
+    }
+    follow
+        .entry(start_symbol.clone())
+        .or_default()
+        .insert(eof_symbol.clone());
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (left, right) in productions {
+            for (i, symbol) in right.iter().enumerate() {
+                let Symbol::NonTerm(b) = symbol else {
+                    continue;
+                };
+                let beta = &right[i + 1..];
+                let mut beta_nullable = true;
+                let mut additions: BTreeSet<T> = BTreeSet::new();
+                for sym in beta {
+                    additions.extend(first_of_symbol(sym));
+                    if !is_nullable(sym) {
+                        beta_nullable = false;
+                        break;
+                    }
+                }
+                let entry = follow.entry(b.clone()).or_default();
+                for t in additions {
+                    changed |= entry.insert(t);
+                }
+                if beta_nullable {
+                    let left_follow = follow.get(left).cloned().unwrap_or_default();
+                    let entry = follow.entry(b.clone()).or_default();
+                    for t in left_follow {
+                        changed |= entry.insert(t);
+                    }
+                }
+            }
+        }
+    }
+
+    follow
+}
+
 impl<NT, T> LR0Parser<NT, T>
 where
     NT: Clone + Eq + Ord + Debug,
@@ -233,7 +633,7 @@ where
     pub fn reset(&mut self) {
         self.cursor = 0;
         self.input.clear();
-        self.stack = vec![0];
+        self.stack = vec![self.start_state_number];
     }
 
     pub fn input(self, input: Vec<T>) -> Self {
@@ -243,6 +643,7 @@ where
             action_table: self.action_table,
             goto_table: self.goto_table,
             stack: self.stack,
+            start_state_number: self.start_state_number,
             rule_table: self.rule_table,
         }
     }
@@ -387,6 +788,161 @@ where
             }
         }
     }
+
+    /// `step_once` と同じ規則で入力を最後まで読み進めつつ、捨てていた構造を
+    /// 具象構文木として組み立てる。Accept に達したら木の根を返す.
+    pub fn parse_to_tree(&mut self) -> Option<ParseTree<NT, T>> {
+        let mut tree_stack: Vec<ParseTree<NT, T>> = vec![];
+        loop {
+            let x = self.input.get(self.cursor)?.clone();
+            let top_index = self.stack.len();
+            let q = *self.stack.get(top_index - 1).unwrap();
+            let action = match self.action_table.get(&(q, x.clone())) {
+                Some(action) => action,
+                None => {
+                    eprintln!("No action for ({},{:?})", q, x);
+                    return None;
+                }
+            };
+            match action {
+                ActionKind::Accept => return tree_stack.pop(),
+                ActionKind::Reduce(rule_number) => {
+                    let lr0item = match self.rule_table.get(*rule_number) {
+                        Some(lr0item) => lr0item.clone(),
+                        None => panic!("can't get r{} from rule_table", rule_number),
+                    };
+                    let pops = lr0item.right.len();
+                    let children = tree_stack.split_off(tree_stack.len() - pops);
+                    for _ in 0..pops {
+                        self.stack.pop();
+                    }
+                    let top_index = self.stack.len() - 1;
+                    if let Some(q) = self.stack.get(top_index) {
+                        let goto_key = (*q, lr0item.left.clone());
+                        if let Some(q_dash) = self.goto_table.get(&goto_key) {
+                            self.stack.push(*q_dash);
+                        } else {
+                            panic!("({},{:?}) -> ?", q, lr0item.left);
+                        }
+                    } else {
+                        panic!("stack is empty this is not acceptable.");
+                    }
+                    tree_stack.push(ParseTree::Node {
+                        rule: *rule_number,
+                        left: lr0item.left,
+                        children,
+                    });
+                }
+                ActionKind::Shift(next_state) => {
+                    tree_stack.push(ParseTree::Leaf(x));
+                    self.stack.push(*next_state);
+                    self.cursor += 1;
+                }
+                ActionKind::Error => {
+                    eprintln!("error detected due to invalid input");
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// `state` で action が定義されている終端記号の集合を、`action_table`
+    /// のキーを走査して求める。エラー時に「ここで妥当だったトークン」を
+    /// 報告するために使う.
+    fn valid_terminals(&self, state: usize) -> BTreeSet<T> {
+        self.action_table
+            .iter()
+            .filter(|((q, _), action)| *q == state && !matches!(action, ActionKind::Error))
+            .map(|((_, t), _)| t.clone())
+            .collect()
+    }
+
+    fn apply_reduce(&mut self, rule_number: usize) {
+        let lr0item = match self.rule_table.get(rule_number) {
+            Some(lr0item) => lr0item.clone(),
+            None => panic!("can't get r{} from rule_table", rule_number),
+        };
+        let pops = lr0item.right.len();
+        for _ in 0..pops {
+            self.stack.pop();
+        }
+        let top_index = self.stack.len() - 1;
+        let q = *self.stack.get(top_index).unwrap();
+        let goto_key = (q, lr0item.left.clone());
+        match self.goto_table.get(&goto_key) {
+            Some(q_dash) => self.stack.push(*q_dash),
+            None => panic!("({},{:?}) -> ?", q, lr0item.left),
+        }
+    }
+
+    /// yacc 風の panic-mode エラー回復を伴って最後まで解析する。
+    /// `(state, token)` に action が無いたびに `SyntaxDiagnostic` を記録し、
+    /// `error_symbol` が指定されていればそれへの goto を持つ状態まで
+    /// `stack` を pop し、その goto 先を積んだうえで、新しい状態が
+    /// shift/reduce できるトークンに出会うまで入力を読み捨てる。
+    /// `error_symbol` が無い場合は goto を探さず読み捨てのみを行う。
+    /// 最初のエラーで止めず、一回の呼び出しで全ての構文エラーを集める.
+    pub fn parse_with_recovery(&mut self, error_symbol: Option<&NT>) -> Vec<SyntaxDiagnostic<T>> {
+        let mut diagnostics = vec![];
+        loop {
+            let Some(x) = self.input.get(self.cursor).cloned() else {
+                break;
+            };
+            let q = *self.stack.last().unwrap();
+            match self.action_table.get(&(q, x.clone())) {
+                Some(ActionKind::Accept) => break,
+                Some(ActionKind::Reduce(rule_number)) => {
+                    let rule_number = *rule_number;
+                    self.apply_reduce(rule_number);
+                }
+                Some(ActionKind::Shift(next_state)) => {
+                    self.stack.push(*next_state);
+                    self.cursor += 1;
+                }
+                Some(ActionKind::Error) | None => {
+                    diagnostics.push(SyntaxDiagnostic {
+                        token_index: self.cursor,
+                        found: x,
+                        expected: self.valid_terminals(q),
+                    });
+                    self.recover(error_symbol);
+                }
+            }
+        }
+        diagnostics
+    }
+
+    fn recover(&mut self, error_symbol: Option<&NT>) {
+        if let Some(error_symbol) = error_symbol {
+            while let Some(&q) = self.stack.last() {
+                if let Some(&target) = self.goto_table.get(&(q, error_symbol.clone())) {
+                    self.stack.push(target);
+                    break;
+                }
+                self.stack.pop();
+            }
+            if self.stack.is_empty() {
+                eprintln!("panic-mode recovery failed: no state has a goto on the error symbol");
+                self.cursor = self.input.len();
+                return;
+            }
+        }
+        loop {
+            let Some(x) = self.input.get(self.cursor) else {
+                return;
+            };
+            let q = *self.stack.last().unwrap();
+            let can_act = matches!(
+                self.action_table.get(&(q, x.clone())),
+                Some(ActionKind::Shift(_)) | Some(ActionKind::Reduce(_)) | Some(ActionKind::Accept)
+            );
+            if can_act {
+                return;
+            }
+            self.cursor += 1;
+        }
+    }
+
     fn dump_remain_input(&self) -> String {
         use std::fmt::Write;
         let mut buffer = String::new();
@@ -409,3 +965,109 @@ where
         buffer
     }
 }
+
+/// `LR0Parser` を一歩ずつ動かしながら観察するための対話的 REPL.
+/// 空白区切りのトークン列を入力すると `tokenize` でトークン化して
+/// 読み込み、以後 `step` / `run` / `back` / `stack` / `table` / `reset`
+/// の各コマンドでオートマトンの動きを一手ずつ追える。行末の `\` は
+/// 継続行を表し、長いトークン列を複数行に分けて入力できる.
+pub struct Repl<NT, T, F>
+where
+    NT: Debug + Clone + Eq + Ord,
+    T: Debug + Clone + Eq + Ord,
+    F: Fn(&str) -> Vec<T>,
+{
+    parser: LR0Parser<NT, T>,
+    tokenize: F,
+    // back で巻き戻すための (cursor, stack) の履歴.
+    history: Vec<(usize, Vec<usize>)>,
+}
+
+impl<NT, T, F> Repl<NT, T, F>
+where
+    NT: Debug + Clone + Eq + Ord,
+    T: Debug + Clone + Eq + Ord,
+    F: Fn(&str) -> Vec<T>,
+{
+    pub fn new(parser: LR0Parser<NT, T>, tokenize: F) -> Self {
+        Self {
+            parser,
+            tokenize,
+            history: vec![],
+        }
+    }
+
+    /// 標準入力から一行ずつ読み込み、コマンドを処理し続ける。EOF で終了する.
+    pub fn run(&mut self) {
+        use std::io::{self, BufRead, Write};
+        let stdin = io::stdin();
+        let mut pending = String::new();
+        loop {
+            print!("lr0> ");
+            io::stdout().flush().unwrap();
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+                break;
+            }
+            let line = line.trim_end_matches('\n').trim_end_matches('\r');
+            if let Some(head) = line.strip_suffix('\\') {
+                pending.push_str(head.trim_end());
+                pending.push(' ');
+                continue;
+            }
+            pending.push_str(line);
+            let command = std::mem::take(&mut pending);
+            let command = command.trim();
+            if !command.is_empty() {
+                self.dispatch(command);
+            }
+        }
+    }
+
+    fn snapshot(&mut self) {
+        self.history.push((self.parser.cursor, self.parser.stack.clone()));
+    }
+
+    fn dispatch(&mut self, command: &str) {
+        match command {
+            "step" => {
+                self.snapshot();
+                self.parser.step_once();
+            }
+            "run" => {
+                while self.parser.cursor < self.parser.input.len() {
+                    self.snapshot();
+                    let cursor_before = self.parser.cursor;
+                    let stack_before = self.parser.stack.clone();
+                    self.parser.step_once();
+                    // Accept, Error and "no action" all leave cursor and the
+                    // stack untouched, so this is how step_once signals "stop
+                    // here" without changing its public return type.
+                    if self.parser.cursor == cursor_before && self.parser.stack == stack_before {
+                        self.history.pop();
+                        break;
+                    }
+                }
+            }
+            "back" => match self.history.pop() {
+                Some((cursor, stack)) => {
+                    self.parser.cursor = cursor;
+                    self.parser.stack = stack;
+                }
+                None => eprintln!("nothing to step back to"),
+            },
+            "stack" => println!("{:?}", self.parser.stack),
+            "table" => println!("{:?}", self.parser.action_table),
+            "reset" => {
+                self.parser.reset();
+                self.history.clear();
+            }
+            line => {
+                self.parser.input = (self.tokenize)(line);
+                self.parser.cursor = 0;
+                self.parser.stack = vec![self.parser.start_state_number];
+                self.history.clear();
+            }
+        }
+    }
+}